@@ -0,0 +1,151 @@
+use crate::db::Database;
+use crate::highlight::Html;
+use crate::id::Id;
+use crate::storage::BlobStore;
+use crate::Error;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+
+/// Wraps the [`Database`] with an in-memory LRU cache of highlighted paste bodies, so repeat
+/// views of the same paste don't re-run syntax highlighting. Also holds the optional blob store
+/// that large paste bodies are offloaded to.
+#[derive(Clone)]
+pub struct Layer {
+    database: Database,
+    cache: Arc<Mutex<LruCache<Id, Arc<Html>>>>,
+    blob_store: Option<Arc<dyn BlobStore>>,
+    blob_threshold: usize,
+}
+
+impl Layer {
+    pub fn new(database: Database, cache_size: NonZeroUsize) -> Self {
+        Self {
+            database,
+            cache: Arc::new(Mutex::new(LruCache::new(cache_size))),
+            blob_store: None,
+            blob_threshold: usize::MAX,
+        }
+    }
+
+    pub fn with_storage(mut self, blob_store: Option<Arc<dyn BlobStore>>, threshold: usize) -> Self {
+        self.blob_store = blob_store;
+        self.blob_threshold = threshold;
+        self
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    pub fn blob_store(&self) -> Option<&Arc<dyn BlobStore>> {
+        self.blob_store.as_ref()
+    }
+
+    pub fn get_highlighted(&self, id: &Id) -> Option<Arc<Html>> {
+        self.cache.lock().unwrap().get(id).cloned()
+    }
+
+    /// Checks whether a paste's expiry has already passed, so a cache hit can be rejected without
+    /// waiting for the delete job to invalidate it.
+    pub fn is_expired(&self, id: &Id) -> Result<bool, Error> {
+        let expires = self.database.get_expires(id)?;
+        Ok(expires.is_some_and(|expires| expires <= OffsetDateTime::now_utc().unix_timestamp()))
+    }
+
+    pub fn insert_highlighted(&self, id: Id, html: Html) {
+        self.cache.lock().unwrap().put(id, Arc::new(html));
+    }
+
+    pub fn invalidate(&self, id: &Id) {
+        self.cache.lock().unwrap().pop(id);
+    }
+
+    /// Stores a paste body, offloading it to the blob store when both a store is configured and
+    /// `body` exceeds the configured threshold; otherwise the body is kept inline.
+    pub async fn insert_paste(
+        &self,
+        id: Id,
+        body: String,
+        extension: Option<String>,
+        expires: Option<i64>,
+    ) -> Result<(), Error> {
+        let entry = if let (Some(store), true) = (&self.blob_store, body.len() > self.blob_threshold) {
+            let locator = store.put(&id, body.as_bytes()).await?;
+            // Bodies are content-addressed, so an identical paste elsewhere in the store shares
+            // this locator; track the reference so deleting one paste doesn't orphan the other.
+            self.database.retain_blob(&locator)?;
+            crate::db::Entry {
+                text: None,
+                storage_locator: Some(locator),
+                extension,
+                burn_after_reading: false,
+                uid: None,
+                expires: None,
+            }
+        } else {
+            crate::db::Entry {
+                text: Some(body),
+                storage_locator: None,
+                extension,
+                burn_after_reading: false,
+                uid: None,
+                expires: None,
+            }
+        };
+
+        self.database.insert(id.clone(), entry, expires)?;
+
+        if let Some(expires) = expires {
+            self.database.enqueue_delete_job(&id, expires)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads a paste's body, resolving it from the blob store if it was offloaded there. Returns
+    /// [`Error::NotFound`] if the paste has an expiry timestamp that has already passed, even if
+    /// the delete job for it hasn't run yet.
+    pub async fn load_paste(&self, id: &Id) -> Result<(String, Option<String>), Error> {
+        let entry = self.database.get(id)?;
+
+        if let Some(expires) = entry.expires {
+            if expires <= OffsetDateTime::now_utc().unix_timestamp() {
+                return Err(Error::NotFound);
+            }
+        }
+
+        let body = match (entry.text, entry.storage_locator) {
+            (Some(text), _) => text,
+            (None, Some(locator)) => {
+                let store = self
+                    .blob_store
+                    .as_ref()
+                    .ok_or_else(|| Error::Storage("paste has no storage backend configured".into()))?;
+                let bytes = store.get(&locator).await?;
+                String::from_utf8(bytes).map_err(|err| Error::Storage(err.to_string()))?
+            }
+            (None, None) => return Err(Error::Storage("paste has no body".into())),
+        };
+
+        Ok((body, entry.extension))
+    }
+
+    /// Deletes a paste's metadata, cached highlight, and (if this was the last paste referencing
+    /// it) its blob.
+    pub async fn remove_paste(&self, id: &Id) -> Result<(), Error> {
+        let entry = self.database.get(id)?;
+
+        if let (Some(store), Some(locator)) = (&self.blob_store, entry.storage_locator) {
+            if self.database.release_blob(&locator)? {
+                store.delete(&locator).await?;
+            }
+        }
+
+        self.database.delete(id)?;
+        self.invalidate(id);
+
+        Ok(())
+    }
+}
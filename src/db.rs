@@ -0,0 +1,203 @@
+#[cfg(feature = "postgres")]
+mod postgres;
+mod sqlite;
+
+use crate::id::Id;
+use crate::Error;
+use std::sync::Arc;
+
+/// Where to open the SQLite database from.
+pub enum Open {
+    /// Open an in-memory database, wiped on restart. Useful for tests and quick trials.
+    Memory,
+    /// Open (and create if missing) a database file at the given path.
+    Path(std::path::PathBuf),
+}
+
+/// A single paste entry as stored in the database.
+///
+/// Exactly one of `text` and `storage_locator` is set: small bodies are kept inline in `text`,
+/// while bodies over the configured threshold are offloaded to a [`crate::storage::BlobStore`]
+/// and referenced by `storage_locator` instead.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub text: Option<String>,
+    pub storage_locator: Option<String>,
+    pub extension: Option<String>,
+    pub burn_after_reading: bool,
+    pub uid: Option<i64>,
+    /// Unix timestamp after which the paste is considered expired, if one was set on insert.
+    pub expires: Option<i64>,
+}
+
+/// A due unit of deferred work claimed from the `jobs` table by a worker.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: i64,
+    pub paste_id: Id,
+    pub attempts: i32,
+}
+
+/// Persistence operations any relational backend (SQLite, PostgreSQL, ...) must provide.
+///
+/// Implementations should map a missing row to [`Error::NotFound`] rather than bubbling up a
+/// driver-specific "no rows" error, so callers get a backend-agnostic way to distinguish "not
+/// found" from a genuine failure.
+pub trait Backend: Send + Sync {
+    fn insert(&self, id: Id, entry: Entry, expires: Option<i64>) -> Result<(), Error>;
+    fn get(&self, id: &Id) -> Result<Entry, Error>;
+    /// Looks up just a paste's expiry, without fetching its (possibly large) body.
+    fn get_expires(&self, id: &Id) -> Result<Option<i64>, Error>;
+    fn delete(&self, id: &Id) -> Result<(), Error>;
+    fn is_user_blocked(&self, name: &str) -> Result<bool, Error>;
+    fn block_user(&self, name: &str) -> Result<(), Error>;
+    fn insert_refresh_token(
+        &self,
+        token_hash: &str,
+        user_name: &str,
+        role: &str,
+        issued_at: i64,
+        expires_at: i64,
+    ) -> Result<(), Error>;
+    fn get_valid_refresh_token(&self, token_hash: &str) -> Result<(String, String), Error>;
+    fn revoke_refresh_tokens(&self, user_name: &str) -> Result<(), Error>;
+
+    /// Schedules `id` for deletion at `run_at` (a unix timestamp), surviving process restarts.
+    fn enqueue_delete_job(&self, id: &Id, run_at: i64) -> Result<(), Error>;
+    /// Atomically claims up to `limit` pending jobs due at or before `now`, so that concurrent
+    /// workers don't double-run the same job.
+    fn claim_due_jobs(&self, now: i64, limit: i64) -> Result<Vec<Job>, Error>;
+    /// Marks a claimed job as successfully completed.
+    fn complete_job(&self, job_id: i64) -> Result<(), Error>;
+    /// Releases a claimed job back to pending, to be retried at `next_attempt_at`.
+    fn fail_job(&self, job_id: i64, next_attempt_at: i64, error: &str) -> Result<(), Error>;
+
+    /// Records a new reference to a content-addressed blob, since identical paste bodies share one
+    /// locator.
+    fn retain_blob(&self, locator: &str) -> Result<(), Error>;
+    /// Releases a reference to a blob, returning `true` once the last reference is gone and the
+    /// blob itself is safe to delete from the store.
+    fn release_blob(&self, locator: &str) -> Result<bool, Error>;
+}
+
+/// Database handle, cheaply cloneable and safe to share across tasks. Delegates to whichever
+/// [`Backend`] was selected at startup.
+#[derive(Clone)]
+pub struct Database {
+    backend: Arc<dyn Backend>,
+}
+
+impl Database {
+    /// Open the default SQLite backend.
+    pub fn new(method: Open) -> Result<Self, Error> {
+        Ok(Self {
+            backend: Arc::new(sqlite::Sqlite::open(method)?),
+        })
+    }
+
+    /// Connect to whichever backend `url`'s scheme names, the way [`crate::storage::from_uri`]
+    /// dispatches on a storage URI's scheme. Recognizes `sqlite://<path>` (or `sqlite://` /
+    /// `sqlite://:memory:` for an in-memory database) and, with the `postgres` feature enabled,
+    /// `postgres://...` or `postgresql://...`.
+    pub fn connect(url: &str) -> Result<Self, Error> {
+        if let Some(rest) = url.strip_prefix("sqlite://") {
+            let method = if rest.is_empty() || rest == ":memory:" {
+                Open::Memory
+            } else {
+                Open::Path(std::path::PathBuf::from(rest))
+            };
+
+            return Self::new(method);
+        }
+
+        #[cfg(feature = "postgres")]
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            return Ok(Self {
+                backend: Arc::new(postgres::Postgres::connect(url)?),
+            });
+        }
+
+        Err(Error::Storage(format!("unrecognized database URL: {url}")))
+    }
+
+    pub fn insert(&self, id: Id, entry: Entry, expires: Option<i64>) -> Result<(), Error> {
+        self.backend.insert(id, entry, expires)
+    }
+
+    pub fn get(&self, id: &Id) -> Result<Entry, Error> {
+        self.backend.get(id)
+    }
+
+    /// Looks up just a paste's expiry, without fetching its (possibly large) body.
+    pub fn get_expires(&self, id: &Id) -> Result<Option<i64>, Error> {
+        self.backend.get_expires(id)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<(), Error> {
+        self.backend.delete(id)
+    }
+
+    /// Returns whether the named user is currently blocked from authenticating.
+    pub fn is_user_blocked(&self, name: &str) -> Result<bool, Error> {
+        self.backend.is_user_blocked(name)
+    }
+
+    /// Marks a user as blocked, preventing future authentication.
+    pub fn block_user(&self, name: &str) -> Result<(), Error> {
+        self.backend.block_user(name)
+    }
+
+    /// Stores the SHA-256 hash of a freshly issued refresh token.
+    pub fn insert_refresh_token(
+        &self,
+        token_hash: &str,
+        user_name: &str,
+        role: &str,
+        issued_at: i64,
+        expires_at: i64,
+    ) -> Result<(), Error> {
+        self.backend
+            .insert_refresh_token(token_hash, user_name, role, issued_at, expires_at)
+    }
+
+    /// Looks up a refresh token by its hash, returning the associated user and role if it is
+    /// neither revoked nor expired.
+    pub fn get_valid_refresh_token(&self, token_hash: &str) -> Result<(String, String), Error> {
+        self.backend.get_valid_refresh_token(token_hash)
+    }
+
+    /// Revokes every refresh token issued to a user, e.g. for a forced logout.
+    pub fn revoke_refresh_tokens(&self, user_name: &str) -> Result<(), Error> {
+        self.backend.revoke_refresh_tokens(user_name)
+    }
+
+    /// Schedules `id` for deletion at `run_at` (a unix timestamp), surviving process restarts.
+    pub fn enqueue_delete_job(&self, id: &Id, run_at: i64) -> Result<(), Error> {
+        self.backend.enqueue_delete_job(id, run_at)
+    }
+
+    /// Atomically claims up to `limit` pending jobs due at or before `now`.
+    pub fn claim_due_jobs(&self, now: i64, limit: i64) -> Result<Vec<Job>, Error> {
+        self.backend.claim_due_jobs(now, limit)
+    }
+
+    /// Marks a claimed job as successfully completed.
+    pub fn complete_job(&self, job_id: i64) -> Result<(), Error> {
+        self.backend.complete_job(job_id)
+    }
+
+    /// Releases a claimed job back to pending, to be retried at `next_attempt_at`.
+    pub fn fail_job(&self, job_id: i64, next_attempt_at: i64, error: &str) -> Result<(), Error> {
+        self.backend.fail_job(job_id, next_attempt_at, error)
+    }
+
+    /// Records a new reference to a content-addressed blob.
+    pub fn retain_blob(&self, locator: &str) -> Result<(), Error> {
+        self.backend.retain_blob(locator)
+    }
+
+    /// Releases a reference to a blob, returning `true` once it is safe to delete from the store.
+    pub fn release_blob(&self, locator: &str) -> Result<bool, Error> {
+        self.backend.release_blob(locator)
+    }
+}
@@ -0,0 +1,272 @@
+use super::{Backend, Entry, Job};
+use crate::id::Id;
+use crate::Error;
+use postgres::{Client, NoTls};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// How long a claimed job is given to run before another worker may reclaim it, in case the
+/// worker that claimed it crashed mid-job.
+const CLAIM_LEASE_SECS: i64 = 300;
+
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS entries (
+        id TEXT PRIMARY KEY,
+        text TEXT,
+        storage_locator TEXT,
+        extension TEXT,
+        burn_after_reading BOOLEAN,
+        uid BIGINT,
+        expires BIGINT
+    );",
+    "CREATE TABLE IF NOT EXISTS users (
+        name TEXT PRIMARY KEY,
+        blocked BOOLEAN NOT NULL DEFAULT FALSE
+    );",
+    "CREATE TABLE IF NOT EXISTS refresh_tokens (
+        token_hash TEXT PRIMARY KEY,
+        user_name TEXT NOT NULL,
+        role TEXT NOT NULL,
+        issued_at BIGINT NOT NULL,
+        expires_at BIGINT NOT NULL,
+        revoked BOOLEAN NOT NULL DEFAULT FALSE
+    );",
+    "CREATE TABLE IF NOT EXISTS jobs (
+        id BIGSERIAL PRIMARY KEY,
+        paste_id TEXT NOT NULL,
+        run_at BIGINT NOT NULL,
+        claimed_at BIGINT,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        status TEXT NOT NULL DEFAULT 'pending',
+        last_error TEXT
+    );",
+    "CREATE TABLE IF NOT EXISTS blob_refs (
+        locator TEXT PRIMARY KEY,
+        count BIGINT NOT NULL
+    );",
+];
+
+/// PostgreSQL backend for shared-database, horizontally scaled deployments, enabled via the
+/// `postgres` feature. Runs its own migration set independent of the SQLite one.
+pub struct Postgres {
+    client: Mutex<Client>,
+}
+
+impl Postgres {
+    pub fn connect(url: &str) -> Result<Self, Error> {
+        let mut client = Client::connect(url, NoTls)?;
+
+        for migration in MIGRATIONS {
+            client.batch_execute(migration)?;
+        }
+
+        Ok(Self {
+            client: Mutex::new(client),
+        })
+    }
+}
+
+impl Backend for Postgres {
+    fn insert(&self, id: Id, entry: Entry, expires: Option<i64>) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO entries (id, text, storage_locator, extension, burn_after_reading, uid, expires)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &id.as_str(),
+                &entry.text,
+                &entry.storage_locator,
+                &entry.extension,
+                &entry.burn_after_reading,
+                &entry.uid,
+                &expires,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn get(&self, id: &Id) -> Result<Entry, Error> {
+        let mut client = self.client.lock().unwrap();
+        let row = client
+            .query_opt(
+                "SELECT text, storage_locator, extension, burn_after_reading, uid, expires FROM entries WHERE id = $1",
+                &[&id.as_str()],
+            )?
+            .ok_or(Error::NotFound)?;
+
+        Ok(Entry {
+            text: row.get(0),
+            storage_locator: row.get(1),
+            extension: row.get(2),
+            burn_after_reading: row.get(3),
+            uid: row.get(4),
+            expires: row.get(5),
+        })
+    }
+
+    fn get_expires(&self, id: &Id) -> Result<Option<i64>, Error> {
+        let mut client = self.client.lock().unwrap();
+        let row = client
+            .query_opt(
+                "SELECT expires FROM entries WHERE id = $1",
+                &[&id.as_str()],
+            )?
+            .ok_or(Error::NotFound)?;
+
+        Ok(row.get(0))
+    }
+
+    fn delete(&self, id: &Id) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute("DELETE FROM entries WHERE id = $1", &[&id.as_str()])?;
+        Ok(())
+    }
+
+    fn is_user_blocked(&self, name: &str) -> Result<bool, Error> {
+        let mut client = self.client.lock().unwrap();
+        let blocked = client
+            .query_opt("SELECT blocked FROM users WHERE name = $1", &[&name])?
+            .map_or(false, |row| row.get(0));
+
+        Ok(blocked)
+    }
+
+    fn block_user(&self, name: &str) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO users (name, blocked) VALUES ($1, TRUE)
+             ON CONFLICT (name) DO UPDATE SET blocked = TRUE",
+            &[&name],
+        )?;
+        Ok(())
+    }
+
+    fn insert_refresh_token(
+        &self,
+        token_hash: &str,
+        user_name: &str,
+        role: &str,
+        issued_at: i64,
+        expires_at: i64,
+    ) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO refresh_tokens (token_hash, user_name, role, issued_at, expires_at, revoked)
+             VALUES ($1, $2, $3, $4, $5, FALSE)",
+            &[&token_hash, &user_name, &role, &issued_at, &expires_at],
+        )?;
+        Ok(())
+    }
+
+    fn get_valid_refresh_token(&self, token_hash: &str) -> Result<(String, String), Error> {
+        let mut client = self.client.lock().unwrap();
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+        let row = client
+            .query_opt(
+                "SELECT user_name, role FROM refresh_tokens
+                 WHERE token_hash = $1 AND revoked = FALSE AND expires_at > $2",
+                &[&token_hash, &now],
+            )?
+            .ok_or(Error::TokenRevoked)?;
+
+        Ok((row.get(0), row.get(1)))
+    }
+
+    fn revoke_refresh_tokens(&self, user_name: &str) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "UPDATE refresh_tokens SET revoked = TRUE WHERE user_name = $1",
+            &[&user_name],
+        )?;
+        Ok(())
+    }
+
+    fn enqueue_delete_job(&self, id: &Id, run_at: i64) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO jobs (paste_id, run_at) VALUES ($1, $2)",
+            &[&id.as_str(), &run_at],
+        )?;
+        Ok(())
+    }
+
+    fn claim_due_jobs(&self, now: i64, limit: i64) -> Result<Vec<Job>, Error> {
+        let mut client = self.client.lock().unwrap();
+        let lease_cutoff = now - CLAIM_LEASE_SECS;
+        let mut transaction = client.transaction()?;
+
+        let rows = transaction.query(
+            "SELECT id, paste_id, attempts FROM jobs
+             WHERE status = 'pending' AND run_at <= $1
+               AND (claimed_at IS NULL OR claimed_at < $2)
+             ORDER BY run_at LIMIT $3
+             FOR UPDATE SKIP LOCKED",
+            &[&now, &lease_cutoff, &limit],
+        )?;
+
+        let jobs: Vec<Job> = rows
+            .iter()
+            .map(|row| Job {
+                id: row.get(0),
+                paste_id: Id::from_str(row.get::<_, String>(1).as_str()).unwrap_or_else(|_| Id::new()),
+                attempts: row.get(2),
+            })
+            .collect();
+
+        for job in &jobs {
+            transaction.execute(
+                "UPDATE jobs SET claimed_at = $1 WHERE id = $2",
+                &[&now, &job.id],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(jobs)
+    }
+
+    fn complete_job(&self, job_id: i64) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute("UPDATE jobs SET status = 'done' WHERE id = $1", &[&job_id])?;
+        Ok(())
+    }
+
+    fn fail_job(&self, job_id: i64, next_attempt_at: i64, error: &str) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "UPDATE jobs SET run_at = $1, claimed_at = NULL, attempts = attempts + 1, last_error = $2
+             WHERE id = $3",
+            &[&next_attempt_at, &error, &job_id],
+        )?;
+        Ok(())
+    }
+
+    fn retain_blob(&self, locator: &str) -> Result<(), Error> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO blob_refs (locator, count) VALUES ($1, 1)
+             ON CONFLICT (locator) DO UPDATE SET count = blob_refs.count + 1",
+            &[&locator],
+        )?;
+        Ok(())
+    }
+
+    fn release_blob(&self, locator: &str) -> Result<bool, Error> {
+        let mut client = self.client.lock().unwrap();
+        let row = client
+            .query_opt(
+                "UPDATE blob_refs SET count = count - 1 WHERE locator = $1 RETURNING count",
+                &[&locator],
+            )?;
+
+        match row {
+            Some(row) if row.get::<_, i64>(0) > 0 => Ok(false),
+            Some(_) => {
+                client.execute("DELETE FROM blob_refs WHERE locator = $1", &[&locator])?;
+                Ok(true)
+            }
+            None => Ok(true),
+        }
+    }
+}
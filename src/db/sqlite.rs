@@ -0,0 +1,307 @@
+use super::{Backend, Entry, Job, Open};
+use crate::id::Id;
+use crate::Error;
+use rusqlite::{Connection, OptionalExtension};
+use rusqlite_migration::{Migrations, M};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up(
+            "CREATE TABLE entries (
+                id TEXT PRIMARY KEY,
+                text TEXT,
+                storage_locator TEXT,
+                extension TEXT,
+                burn_after_reading BOOLEAN,
+                uid INTEGER,
+                expires INTEGER
+            );",
+        ),
+        M::up(
+            "CREATE TABLE users (
+                name TEXT PRIMARY KEY,
+                blocked BOOLEAN NOT NULL DEFAULT FALSE
+            );",
+        ),
+        M::up(
+            "CREATE TABLE refresh_tokens (
+                token_hash TEXT PRIMARY KEY,
+                user_name TEXT NOT NULL,
+                role TEXT NOT NULL,
+                issued_at INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL,
+                revoked BOOLEAN NOT NULL DEFAULT FALSE
+            );",
+        ),
+        M::up(
+            "CREATE TABLE jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                paste_id TEXT NOT NULL,
+                run_at INTEGER NOT NULL,
+                claimed_at INTEGER,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'pending',
+                last_error TEXT
+            );",
+        ),
+        M::up(
+            "CREATE TABLE blob_refs (
+                locator TEXT PRIMARY KEY,
+                count INTEGER NOT NULL
+            );",
+        ),
+    ])
+}
+
+/// How long a claimed job is given to run before another worker may reclaim it, in case the
+/// worker that claimed it crashed mid-job.
+const CLAIM_LEASE_SECS: i64 = 300;
+
+/// The default backend, storing everything in a local SQLite file (or in memory).
+pub struct Sqlite {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Sqlite {
+    pub fn open(method: Open) -> Result<Self, Error> {
+        let mut conn = match method {
+            Open::Memory => Connection::open_in_memory()?,
+            Open::Path(path) => Connection::open(path)?,
+        };
+
+        migrations().to_latest(&mut conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+}
+
+impl Backend for Sqlite {
+    fn insert(&self, id: Id, entry: Entry, expires: Option<i64>) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO entries (id, text, storage_locator, extension, burn_after_reading, uid, expires)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                id.as_str(),
+                entry.text,
+                entry.storage_locator,
+                entry.extension,
+                entry.burn_after_reading,
+                entry.uid,
+                expires
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn get(&self, id: &Id) -> Result<Entry, Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT text, storage_locator, extension, burn_after_reading, uid, expires FROM entries WHERE id = ?1",
+            [id.as_str()],
+            |row| {
+                Ok(Entry {
+                    text: row.get(0)?,
+                    storage_locator: row.get(1)?,
+                    extension: row.get(2)?,
+                    burn_after_reading: row.get(3)?,
+                    uid: row.get(4)?,
+                    expires: row.get(5)?,
+                })
+            },
+        )
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+            err => Error::from(err),
+        })
+    }
+
+    fn get_expires(&self, id: &Id) -> Result<Option<i64>, Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT expires FROM entries WHERE id = ?1",
+            [id.as_str()],
+            |row| row.get(0),
+        )
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+            err => Error::from(err),
+        })
+    }
+
+    fn delete(&self, id: &Id) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM entries WHERE id = ?1", [id.as_str()])?;
+        Ok(())
+    }
+
+    fn is_user_blocked(&self, name: &str) -> Result<bool, Error> {
+        let conn = self.conn.lock().unwrap();
+        let blocked = conn
+            .query_row(
+                "SELECT blocked FROM users WHERE name = ?1",
+                [name],
+                |row| row.get::<_, bool>(0),
+            )
+            .or_else(|err| match err {
+                rusqlite::Error::QueryReturnedNoRows => Ok(false),
+                err => Err(err),
+            })?;
+
+        Ok(blocked)
+    }
+
+    fn block_user(&self, name: &str) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO users (name, blocked) VALUES (?1, TRUE)
+             ON CONFLICT(name) DO UPDATE SET blocked = TRUE",
+            [name],
+        )?;
+        Ok(())
+    }
+
+    fn insert_refresh_token(
+        &self,
+        token_hash: &str,
+        user_name: &str,
+        role: &str,
+        issued_at: i64,
+        expires_at: i64,
+    ) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO refresh_tokens (token_hash, user_name, role, issued_at, expires_at, revoked)
+             VALUES (?1, ?2, ?3, ?4, ?5, FALSE)",
+            rusqlite::params![token_hash, user_name, role, issued_at, expires_at],
+        )?;
+        Ok(())
+    }
+
+    fn get_valid_refresh_token(&self, token_hash: &str) -> Result<(String, String), Error> {
+        let conn = self.conn.lock().unwrap();
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+        conn.query_row(
+            "SELECT user_name, role FROM refresh_tokens
+             WHERE token_hash = ?1 AND revoked = FALSE AND expires_at > ?2",
+            rusqlite::params![token_hash, now],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => Error::TokenRevoked,
+            err => Error::from(err),
+        })
+    }
+
+    fn revoke_refresh_tokens(&self, user_name: &str) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE refresh_tokens SET revoked = TRUE WHERE user_name = ?1",
+            [user_name],
+        )?;
+        Ok(())
+    }
+
+    fn enqueue_delete_job(&self, id: &Id, run_at: i64) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO jobs (paste_id, run_at) VALUES (?1, ?2)",
+            rusqlite::params![id.as_str(), run_at],
+        )?;
+        Ok(())
+    }
+
+    fn claim_due_jobs(&self, now: i64, limit: i64) -> Result<Vec<Job>, Error> {
+        let mut conn = self.conn.lock().unwrap();
+        // BEGIN IMMEDIATE takes the write lock up front, so two processes sharing this file can't
+        // both read the same pending rows before either writes `claimed_at`.
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let lease_cutoff = now - CLAIM_LEASE_SECS;
+
+        let mut stmt = tx.prepare(
+            "SELECT id, paste_id, attempts FROM jobs
+             WHERE status = 'pending' AND run_at <= ?1
+               AND (claimed_at IS NULL OR claimed_at < ?2)
+             ORDER BY run_at LIMIT ?3",
+        )?;
+        let jobs = stmt
+            .query_map(rusqlite::params![now, lease_cutoff, limit], |row| {
+                Ok(Job {
+                    id: row.get(0)?,
+                    paste_id: Id::from_str(&row.get::<_, String>(1)?)
+                        .unwrap_or_else(|_| Id::new()),
+                    attempts: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        drop(stmt);
+
+        for job in &jobs {
+            tx.execute(
+                "UPDATE jobs SET claimed_at = ?1 WHERE id = ?2",
+                rusqlite::params![now, job.id],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(jobs)
+    }
+
+    fn complete_job(&self, job_id: i64) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE jobs SET status = 'done' WHERE id = ?1",
+            [job_id],
+        )?;
+        Ok(())
+    }
+
+    fn fail_job(&self, job_id: i64, next_attempt_at: i64, error: &str) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE jobs SET run_at = ?1, claimed_at = NULL, attempts = attempts + 1, last_error = ?2
+             WHERE id = ?3",
+            rusqlite::params![next_attempt_at, error, job_id],
+        )?;
+        Ok(())
+    }
+
+    fn retain_blob(&self, locator: &str) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO blob_refs (locator, count) VALUES (?1, 1)
+             ON CONFLICT(locator) DO UPDATE SET count = count + 1",
+            [locator],
+        )?;
+        Ok(())
+    }
+
+    fn release_blob(&self, locator: &str) -> Result<bool, Error> {
+        let conn = self.conn.lock().unwrap();
+        let remaining: Option<i64> = conn
+            .query_row(
+                "UPDATE blob_refs SET count = count - 1 WHERE locator = ?1 RETURNING count",
+                [locator],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match remaining {
+            // Another paste still references this blob: leave it in place.
+            Some(count) if count > 0 => Ok(false),
+            Some(_) => {
+                conn.execute("DELETE FROM blob_refs WHERE locator = ?1", [locator])?;
+                Ok(true)
+            }
+            // No ref row (e.g. a paste stored before this table existed): nothing else is known
+            // to reference it, so it's safe to delete.
+            None => Ok(true),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! An optional Gemini protocol (`gemini://`) frontend for reading pastes, enabled via the
+//! `gemini` feature flag. Runs alongside the HTTP server, reusing the same [`cache::Layer`] for
+//! storage and expiry.
+use crate::cache::Layer;
+use crate::id::Id;
+use crate::Error;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::{self, Certificate, PrivateKey};
+use tokio_rustls::TlsAcceptor;
+
+const STATUS_SUCCESS: &str = "20 text/gemini\r\n";
+const STATUS_NOT_FOUND: &str = "51 Not found\r\n";
+const STATUS_BAD_REQUEST: &str = "59 Bad request\r\n";
+
+fn load_tls_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor, Error> {
+    let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(std::fs::File::open(
+        cert_path,
+    )?))?
+    .into_iter()
+    .map(Certificate)
+    .collect();
+
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut std::io::BufReader::new(
+        std::fs::File::open(key_path)?,
+    ))?;
+
+    let key = PrivateKey(
+        keys.pop()
+            .ok_or_else(|| Error::Storage("no private key found in Gemini TLS key file".into()))?,
+    );
+
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| Error::Storage(format!("invalid Gemini TLS configuration: {err}")))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Extracts the paste id from a single-line Gemini request URL, e.g. `gemini://host/AbCd1234`.
+fn parse_id(line: &str) -> Option<Id> {
+    let url = url::Url::parse(line.trim()).ok()?;
+    let path = url.path().trim_start_matches('/');
+
+    if path.is_empty() {
+        return None;
+    }
+
+    Id::from_str(path).ok()
+}
+
+/// Runs the Gemini server until the process is shut down, serving pastes read-only.
+pub async fn serve(cache: Layer, addr: SocketAddr, cert_path: &Path, key_path: &Path) -> Result<(), Error> {
+    let acceptor = load_tls_acceptor(cert_path, key_path)?;
+    let listener = TcpListener::bind(addr).await?;
+
+    tracing::debug!("serving gemini on {addr}");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let acceptor = acceptor.clone();
+        let cache = cache.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = handle_connection(stream, acceptor, cache).await {
+                tracing::warn!("gemini connection error: {err}");
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: tokio::net::TcpStream,
+    acceptor: TlsAcceptor,
+    cache: Layer,
+) -> Result<(), Error> {
+    let mut stream = acceptor
+        .accept(stream)
+        .await
+        .map_err(|err| Error::Storage(err.to_string()))?;
+
+    let mut buf = vec![0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let line = String::from_utf8_lossy(&buf[..n]);
+
+    let Some(id) = parse_id(&line) else {
+        stream.write_all(STATUS_BAD_REQUEST.as_bytes()).await?;
+        return Ok(());
+    };
+
+    match cache.load_paste(&id).await {
+        Ok((text, _)) => {
+            stream.write_all(STATUS_SUCCESS.as_bytes()).await?;
+            stream.write_all(b"```\n").await?;
+            stream.write_all(text.as_bytes()).await?;
+            stream.write_all(b"\n```\n").await?;
+        }
+        Err(_) => {
+            stream.write_all(STATUS_NOT_FOUND.as_bytes()).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::cache::Layer;
+use crate::highlight;
+use crate::id::Id;
+use crate::pages;
+use crate::token::{AuthUser, Issuer};
+use crate::{Error, Router};
+use axum::extract::{Form, Path};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Extension, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(index).post(insert))
+        .route("/:id", get(show).delete(remove))
+        .route("/auth/refresh", post(refresh))
+}
+
+async fn index() -> axum::response::Html<String> {
+    pages::index()
+}
+
+#[derive(Deserialize)]
+struct InsertForm {
+    text: String,
+    extension: Option<String>,
+    /// Seconds from now after which the paste should be deleted, if any.
+    expires_in: Option<i64>,
+}
+
+async fn insert(
+    Extension(cache): Extension<Layer>,
+    AuthUser(_user): AuthUser,
+    Form(form): Form<InsertForm>,
+) -> Result<String, StatusCode> {
+    let id = Id::new();
+    let expires = form
+        .expires_in
+        .map(|secs| OffsetDateTime::now_utc().unix_timestamp() + secs);
+
+    cache
+        .insert_paste(id.clone(), form.text, form.extension, expires)
+        .await
+        .map_err(StatusCode::from)?;
+
+    Ok(id.to_string())
+}
+
+async fn show(
+    Extension(cache): Extension<Layer>,
+    Path(id): Path<Id>,
+) -> Result<axum::response::Html<String>, StatusCode> {
+    if let Some(html) = cache.get_highlighted(&id) {
+        if cache.is_expired(&id).map_err(StatusCode::from)? {
+            return Err(StatusCode::NOT_FOUND);
+        }
+
+        return Ok(pages::paste(&html));
+    }
+
+    let (text, extension) = cache.load_paste(&id).await.map_err(StatusCode::from)?;
+    let html = highlight::highlight(&text, extension.as_deref()).map_err(StatusCode::from)?;
+    cache.insert_highlighted(id, html.clone());
+
+    Ok(pages::paste(&html))
+}
+
+async fn remove(
+    Extension(cache): Extension<Layer>,
+    AuthUser(_user): AuthUser,
+    Path(id): Path<Id>,
+) -> Result<StatusCode, StatusCode> {
+    cache.remove_paste(&id).await.map_err(StatusCode::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(serde::Serialize)]
+struct RefreshResponse {
+    access_token: String,
+}
+
+async fn refresh(
+    Extension(cache): Extension<Layer>,
+    Extension(issuer): Extension<Arc<Issuer>>,
+    Json(body): Json<RefreshRequest>,
+) -> Result<Json<RefreshResponse>, StatusCode> {
+    let access_token = issuer
+        .refresh(&body.refresh_token, cache.database())
+        .map_err(StatusCode::from)?;
+
+    Ok(Json(RefreshResponse { access_token }))
+}
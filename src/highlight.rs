@@ -0,0 +1,24 @@
+use crate::Error;
+use once_cell::sync::Lazy;
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+/// Syntax-highlighted HTML for a paste body.
+#[derive(Debug, Clone)]
+pub struct Html(pub String);
+
+/// Render `text` as highlighted HTML, looking up the syntax by file extension.
+pub fn highlight(text: &str, extension: Option<&str>) -> Result<Html, Error> {
+    let syntax = extension
+        .and_then(|ext| SYNTAX_SET.find_syntax_by_extension(ext))
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+
+    let theme = &THEME_SET.themes["base16-ocean.dark"];
+    let html = highlighted_html_for_string(text, &SYNTAX_SET, syntax, theme)?;
+
+    Ok(Html(html))
+}
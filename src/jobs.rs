@@ -0,0 +1,65 @@
+//! A durable worker for the `jobs` table, replacing the old in-process purge loop so scheduled
+//! deletions survive a restart instead of being lost until the next boot.
+use crate::cache::Layer;
+use crate::db::Job;
+use crate::Error;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// How often to poll for due jobs.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// How many jobs a single poll claims at once.
+const BATCH_SIZE: i64 = 50;
+/// Base delay before retrying a failed job; doubled per attempt, capped at `MAX_BACKOFF_SECS`.
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 3600;
+
+fn backoff_secs(attempts: i32) -> i64 {
+    let shift = attempts.clamp(0, 7) as u32;
+    (BASE_BACKOFF_SECS * (1_i64 << shift)).min(MAX_BACKOFF_SECS)
+}
+
+/// Polls the `jobs` table for due deletions and runs them, retrying failures with backoff. Runs
+/// until the process is shut down.
+pub async fn run(layer: Layer) -> Result<(), Error> {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let due = match layer.database().claim_due_jobs(now, BATCH_SIZE) {
+            Ok(due) => due,
+            Err(err) => {
+                // A transient DB hiccup here shouldn't take the HTTP server down with it (this
+                // runs alongside it in the same `tokio::select!`); log and retry next poll.
+                tracing::warn!("failed to claim due jobs: {err}");
+                continue;
+            }
+        };
+
+        for job in due {
+            run_job(&layer, &job, now).await;
+        }
+    }
+}
+
+async fn run_job(layer: &Layer, job: &Job, now: i64) {
+    match layer.remove_paste(&job.paste_id).await {
+        Ok(()) | Err(Error::NotFound) => {
+            if let Err(err) = layer.database().complete_job(job.id) {
+                tracing::warn!("failed to mark job {} complete: {err}", job.id);
+            }
+        }
+        Err(err) => {
+            tracing::warn!("job {} failed (attempt {}): {err}", job.id, job.attempts);
+            let next_attempt_at = now + backoff_secs(job.attempts);
+            if let Err(err) = layer
+                .database()
+                .fail_job(job.id, next_attempt_at, &err.to_string())
+            {
+                tracing::warn!("failed to reschedule job {}: {err}", job.id);
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! A small tower layer that rejects requests whose URI path or query string is too long before
+//! they reach routing, alongside [`tower_http::limit::RequestBodyLimitLayer`] which bounds the
+//! body.
+use axum::body::Body;
+use axum::http::{Request, Response, StatusCode};
+use futures_util::future::BoxFuture;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Layer constructor for [`UriLimit`]. Rejects requests with `414 URI Too Long` when the
+/// percent-decoded path exceeds `max_path_len`, or `400 Bad Request` when the (raw) query string
+/// exceeds `max_query_len`.
+#[derive(Debug, Clone, Copy)]
+pub struct UriLimitLayer {
+    max_path_len: usize,
+    max_query_len: usize,
+}
+
+impl UriLimitLayer {
+    pub fn new(max_path_len: usize, max_query_len: usize) -> Self {
+        Self {
+            max_path_len,
+            max_query_len,
+        }
+    }
+}
+
+impl<S> Layer<S> for UriLimitLayer {
+    type Service = UriLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        UriLimit {
+            inner,
+            max_path_len: self.max_path_len,
+            max_query_len: self.max_query_len,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UriLimit<S> {
+    inner: S,
+    max_path_len: usize,
+    max_query_len: usize,
+}
+
+impl<S> Service<Request<Body>> for UriLimit<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // `req.uri().path()` is the raw, percent-encoded path as received; decode it first so the
+        // limit bounds the path the request actually specifies, not its encoded length.
+        let decoded_path_len = percent_encoding::percent_decode_str(req.uri().path())
+            .decode_utf8()
+            .map(|path| path.len())
+            .unwrap_or(usize::MAX);
+
+        if decoded_path_len > self.max_path_len {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::URI_TOO_LONG;
+            return Box::pin(async move { Ok(response) });
+        }
+
+        if req.uri().query().map_or(0, str::len) > self.max_query_len {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::BAD_REQUEST;
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let mut inner = self.inner.clone();
+        Box::pin(async move { inner.call(req).await })
+    }
+}
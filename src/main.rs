@@ -19,10 +19,15 @@ use tower_http::trace::TraceLayer;
 
 mod cache;
 mod db;
+#[cfg(feature = "gemini")]
+mod gemini;
 mod handler;
 mod highlight;
 mod id;
+mod jobs;
+mod limits;
 mod pages;
+mod storage;
 #[cfg(test)]
 mod test_helpers;
 mod token;
@@ -37,13 +42,19 @@ struct Cli {
 enum Commands {
     /// Run the server
     Serve,
-    /// Issue a new token for a user
+    /// Issue, revoke or block tokens
     Token {
         /// Name of the user for which the token is issued
-        name: String,
+        name: Option<String>,
         /// Use if the user has administrative capabilities
         #[arg(long)]
         is_admin: bool,
+        /// Revoke all outstanding refresh tokens for the named user
+        #[arg(long, conflicts_with = "block")]
+        revoke: Option<String>,
+        /// Block the named user from authenticating, invalidating their access tokens
+        #[arg(long, conflicts_with = "revoke")]
+        block: Option<String>,
     },
 }
 
@@ -80,6 +91,19 @@ pub enum Error {
     TokenCreation(String),
     #[error("failed to validate token: {0}")]
     TokenValidation(String),
+    #[error("refresh token is unknown, revoked or expired")]
+    TokenRevoked,
+    #[error("user is blocked")]
+    UserBlocked,
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("blob storage error: {0}")]
+    Storage(String),
+    #[error("not found")]
+    NotFound,
+    #[cfg(feature = "postgres")]
+    #[error("postgres error: {0}")]
+    Postgres(#[from] postgres::Error),
 }
 
 pub type Router = axum::Router<http_body::Limited<axum::body::Body>>;
@@ -102,15 +126,49 @@ impl From<Error> for StatusCode {
             | Error::SyntaxParsing(_)
             | Error::TokenCreation(_)
             | Error::Axum(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            Error::TokenValidation(_) => StatusCode::UNAUTHORIZED,
+            Error::TokenValidation(_) | Error::TokenRevoked | Error::UserBlocked => {
+                StatusCode::UNAUTHORIZED
+            }
+            Error::Io(_) | Error::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            #[cfg(feature = "postgres")]
+            Error::Postgres(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
+/// Opens the configured database. If neither `WASTEBIN_DATABASE_URL` nor `WASTEBIN_DATABASE_PATH`
+/// is set, falls back to an in-memory database unless `require_persistent` is set, in which case
+/// that's an error instead — used by commands whose whole point is to persist a mutation, where
+/// silently running against a throwaway database would look like it worked while doing nothing.
+fn open_database(require_persistent: bool) -> Result<Database> {
+    const VAR_DATABASE_PATH: &str = "WASTEBIN_DATABASE_PATH";
+    const VAR_DATABASE_URL: &str = "WASTEBIN_DATABASE_URL";
+
+    if let Ok(url) = env::var(VAR_DATABASE_URL) {
+        return Ok(Database::connect(&url)?);
+    }
+
+    match env::var(VAR_DATABASE_PATH) {
+        Ok(path) => Ok(Database::new(db::Open::Path(PathBuf::from(path)))?),
+        Err(VarError::NotUnicode(_)) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{VAR_DATABASE_PATH} contains non-unicode data"),
+        )
+        .into()),
+        Err(VarError::NotPresent) if require_persistent => Err(anyhow::anyhow!(
+            "{VAR_DATABASE_URL} or {VAR_DATABASE_PATH} must be set for this command; refusing to fall back to an in-memory database"
+        )),
+        Err(VarError::NotPresent) => Ok(Database::new(db::Open::Memory)?),
+    }
+}
+
 pub(crate) fn make_app(
     cache_layer: cache::Layer,
     issuer: Arc<token::Issuer>,
     max_body_size: usize,
+    max_uri_path_len: usize,
+    max_query_len: usize,
 ) -> axum::Router {
     Router::new()
         .merge(handler::routes())
@@ -121,22 +179,50 @@ pub(crate) fn make_app(
         .layer(CompressionLayer::new())
         .layer(DefaultBodyLimit::disable())
         .layer(RequestBodyLimitLayer::new(max_body_size))
+        .layer(limits::UriLimitLayer::new(max_uri_path_len, max_query_len))
+}
+
+#[cfg(feature = "gemini")]
+async fn gemini_task(cache: cache::Layer) -> Result<()> {
+    const VAR_GEMINI_ADDRESS_PORT: &str = "WASTEBIN_GEMINI_ADDRESS_PORT";
+    const VAR_GEMINI_TLS_CERT: &str = "WASTEBIN_GEMINI_TLS_CERT";
+    const VAR_GEMINI_TLS_KEY: &str = "WASTEBIN_GEMINI_TLS_KEY";
+
+    let Ok(addr) = env::var(VAR_GEMINI_ADDRESS_PORT) else {
+        return std::future::pending().await;
+    };
+
+    let addr: SocketAddr = addr
+        .parse()
+        .with_context(|| format!("failed to parse {VAR_GEMINI_ADDRESS_PORT}, expect `host:port`"))?;
+
+    let cert_path =
+        PathBuf::from(env::var(VAR_GEMINI_TLS_CERT).with_context(|| format!("{VAR_GEMINI_TLS_CERT} not set"))?);
+    let key_path =
+        PathBuf::from(env::var(VAR_GEMINI_TLS_KEY).with_context(|| format!("{VAR_GEMINI_TLS_KEY} not set"))?);
+
+    gemini::serve(cache, addr, &cert_path, &key_path).await?;
+
+    Ok(())
+}
+
+/// No-op when the `gemini` feature is disabled, so the `tokio::select!` in `serve` always has a
+/// matching branch to poll.
+#[cfg(not(feature = "gemini"))]
+async fn gemini_task(_cache: cache::Layer) -> Result<()> {
+    std::future::pending().await
 }
 
 async fn serve(issuer: token::Issuer) -> Result<()> {
-    const VAR_DATABASE_PATH: &str = "WASTEBIN_DATABASE_PATH";
     const VAR_CACHE_SIZE: &str = "WASTEBIN_CACHE_SIZE";
     const VAR_ADDRESS_PORT: &str = "WASTEBIN_ADDRESS_PORT";
     const VAR_MAX_BODY_SIZE: &str = "WASTEBIN_MAX_BODY_SIZE";
+    const VAR_STORAGE_URI: &str = "WASTEBIN_STORAGE_URI";
+    const VAR_BLOB_THRESHOLD: &str = "WASTEBIN_BLOB_THRESHOLD";
+    const VAR_MAX_URI_PATH_LEN: &str = "WASTEBIN_MAX_URI_PATH_LEN";
+    const VAR_MAX_QUERY_LEN: &str = "WASTEBIN_MAX_QUERY_LEN";
 
-    let database = match env::var(VAR_DATABASE_PATH) {
-        Ok(path) => Ok(Database::new(db::Open::Path(PathBuf::from(path)))?),
-        Err(VarError::NotUnicode(_)) => Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("{VAR_DATABASE_PATH} contains non-unicode data"),
-        )),
-        Err(VarError::NotPresent) => Ok(Database::new(db::Open::Memory)?),
-    }?;
+    let database = open_database(false)?;
 
     let cache_size = env::var(VAR_CACHE_SIZE)
         .map_or_else(
@@ -145,7 +231,18 @@ async fn serve(issuer: token::Issuer) -> Result<()> {
         )
         .with_context(|| format!("failed to parse {VAR_CACHE_SIZE}, expect number of elements"))?;
 
-    let cache_layer = cache::Layer::new(database, cache_size);
+    let blob_store = env::var(VAR_STORAGE_URI)
+        .ok()
+        .map(|uri| storage::from_uri(&uri))
+        .transpose()?
+        .flatten();
+
+    let blob_threshold = env::var(VAR_BLOB_THRESHOLD)
+        .map_or_else(|_| Ok(256 * 1024), |s| s.parse::<usize>())
+        .with_context(|| format!("failed to parse {VAR_BLOB_THRESHOLD}, expect number of bytes"))?;
+
+    let cache_layer =
+        cache::Layer::new(database, cache_size).with_storage(blob_store, blob_threshold);
 
     let addr: SocketAddr = env::var(VAR_ADDRESS_PORT)
         .unwrap_or_else(|_| "0.0.0.0:8088".to_string())
@@ -156,12 +253,28 @@ async fn serve(issuer: token::Issuer) -> Result<()> {
         .map_or_else(|_| Ok(1024 * 1024), |s| s.parse::<usize>())
         .with_context(|| format!("failed to parse {VAR_MAX_BODY_SIZE}, expect number of bytes"))?;
 
+    let max_uri_path_len = env::var(VAR_MAX_URI_PATH_LEN)
+        .map_or_else(|_| Ok(1024), |s| s.parse::<usize>())
+        .with_context(|| format!("failed to parse {VAR_MAX_URI_PATH_LEN}, expect number of bytes"))?;
+
+    let max_query_len = env::var(VAR_MAX_QUERY_LEN)
+        .map_or_else(|_| Ok(4096), |s| s.parse::<usize>())
+        .with_context(|| format!("failed to parse {VAR_MAX_QUERY_LEN}, expect number of bytes"))?;
+
     tracing::debug!("serving on {addr}");
     tracing::debug!("caching {cache_size} paste highlights");
     tracing::debug!("restricting maximum body size to {max_body_size} bytes");
+    tracing::debug!("restricting maximum uri path length to {max_uri_path_len} bytes");
+    tracing::debug!("restricting maximum query length to {max_query_len} bytes");
 
-    let service =
-        make_app(cache_layer.clone(), Arc::new(issuer), max_body_size).into_make_service();
+    let service = make_app(
+        cache_layer.clone(),
+        Arc::new(issuer),
+        max_body_size,
+        max_uri_path_len,
+        max_query_len,
+    )
+    .into_make_service();
 
     let server = Server::bind(&addr)
         .serve(service)
@@ -175,7 +288,10 @@ async fn serve(issuer: token::Issuer) -> Result<()> {
         res = server => {
             res?;
         },
-        res = cache::purge_periodically(cache_layer) => {
+        res = jobs::run(cache_layer.clone()) => {
+            res?;
+        },
+        res = gemini_task(cache_layer) => {
             res?;
         }
     }
@@ -198,7 +314,29 @@ async fn main() -> Result<()> {
 
     match cli.command {
         Commands::Serve => serve(issuer).await,
-        Commands::Token { name, is_admin } => {
+        Commands::Token {
+            name,
+            is_admin,
+            revoke,
+            block,
+        } => {
+            // Issuing, revoking or blocking tokens is only meaningful against a database that
+            // outlives this process.
+            let database = open_database(true)?;
+
+            if let Some(name) = revoke {
+                database.revoke_refresh_tokens(&name)?;
+                println!("Revoked all refresh tokens for {name}");
+                return Ok(());
+            }
+
+            if let Some(name) = block {
+                database.block_user(&name)?;
+                println!("Blocked {name} from authenticating");
+                return Ok(());
+            }
+
+            let name = name.context("NAME is required unless --revoke or --block is given")?;
             let role = if is_admin {
                 token::Role::Admin
             } else {
@@ -206,11 +344,168 @@ async fn main() -> Result<()> {
             };
 
             let user = token::User { name, role };
-            let token = issuer.issue(user)?;
+            let tokens = issuer.issue_pair(user, &database)?;
 
-            println!("Store this token securely: {token}");
+            println!("Access token (short-lived): {}", tokens.access_token);
+            println!("Refresh token (store securely): {}", tokens.refresh_token);
 
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_helpers::{self, Client};
+    use crate::token::{Role, User};
+    use std::sync::Arc;
+
+    fn access_token(app: &test_helpers::TestApp, name: &str) -> String {
+        app.issuer
+            .issue(User {
+                name: name.to_string(),
+                role: Role::User,
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn uri_path_too_long_is_rejected() {
+        let app = test_helpers::make_app().unwrap();
+        let client = Client::new(app.router);
+
+        let path = format!("/{}", "a".repeat(1025));
+        let response = client.get(&path).send().await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::URI_TOO_LONG);
+    }
+
+    #[tokio::test]
+    async fn query_too_long_is_rejected() {
+        let app = test_helpers::make_app().unwrap();
+        let client = Client::new(app.router);
+
+        let path = format!("/?q={}", "a".repeat(4097));
+        let response = client.get(&path).send().await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn insert_without_token_is_rejected() {
+        let app = test_helpers::make_app().unwrap();
+        let client = Client::new(app.router);
+
+        let response = client
+            .post("/")
+            .form(&[("text", "hello")])
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn insert_and_show_roundtrip() {
+        let app = test_helpers::make_app().unwrap();
+        let token = access_token(&app, "alice");
+        let client = Client::new(app.router);
+
+        let response = client
+            .post("/")
+            .header("Authorization", format!("Bearer {token}"))
+            .form(&[("text", "hello, wastebin")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let id = response.text().await.unwrap();
+
+        let response = client.get(&format!("/{id}")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn remove_without_token_is_rejected() {
+        let app = test_helpers::make_app().unwrap();
+        let client = Client::new(app.router);
+
+        let response = client.delete("/abcd1234").send().await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn auth_refresh_mints_a_new_access_token() {
+        let app = test_helpers::make_app().unwrap();
+        let tokens = app
+            .issuer
+            .issue_pair(
+                User {
+                    name: "alice".to_string(),
+                    role: Role::User,
+                },
+                &app.database,
+            )
+            .unwrap();
+        let client = Client::new(app.router);
+
+        let response = client
+            .post("/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": tokens.refresh_token }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["access_token"].is_string());
+    }
+
+    #[tokio::test]
+    async fn blob_offload_roundtrip() {
+        let dir = std::env::temp_dir().join(format!(
+            "wastebin-test-{}-{}",
+            std::process::id(),
+            "blob_offload_roundtrip"
+        ));
+        let store: Arc<dyn crate::storage::BlobStore> =
+            Arc::new(crate::storage::FileStore::new(dir.clone()));
+        let app = test_helpers::make_app_with_storage(Some(store), 4).unwrap();
+        let token = access_token(&app, "alice");
+        let client = Client::new(app.router);
+
+        let body = "this body is well over the four byte threshold";
+        let response = client
+            .post("/")
+            .header("Authorization", format!("Bearer {token}"))
+            .form(&[("text", body)])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let id = response.text().await.unwrap();
+
+        let response = client.get(&format!("/{id}")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[test]
+    fn job_queue_claims_each_due_job_once() {
+        let database = crate::db::Database::new(crate::db::Open::Memory).unwrap();
+        let id = crate::id::Id::new();
+        database.enqueue_delete_job(&id, 0).unwrap();
+
+        let jobs = database.claim_due_jobs(100, 10).unwrap();
+        assert_eq!(jobs.len(), 1);
+
+        // Still within the claim's lease: a second worker must not see it again.
+        let jobs_again = database.claim_due_jobs(100, 10).unwrap();
+        assert!(jobs_again.is_empty());
+
+        database.complete_job(jobs[0].id).unwrap();
+    }
+}
@@ -0,0 +1,20 @@
+use crate::highlight::Html;
+use crate::TITLE;
+
+/// Renders the paste submission form.
+pub fn index() -> axum::response::Html<String> {
+    axum::response::Html(format!(
+        "<!DOCTYPE html><html><head><title>{}</title></head><body>\
+         <form method=\"post\" action=\"/\"><textarea name=\"text\"></textarea>\
+         <button type=\"submit\">Paste</button></form></body></html>",
+        *TITLE
+    ))
+}
+
+/// Renders a stored paste's highlighted body.
+pub fn paste(html: &Html) -> axum::response::Html<String> {
+    axum::response::Html(format!(
+        "<!DOCTYPE html><html><head><title>{}</title></head><body>{}</body></html>",
+        *TITLE, html.0
+    ))
+}
@@ -0,0 +1,290 @@
+use crate::id::Id;
+use crate::Error;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A pluggable backend for paste bodies that are too large to keep inline in the database.
+///
+/// Implementations are addressed by an opaque locator string that the store hands back from
+/// [`BlobStore::put`]; callers persist that locator alongside the paste's metadata and pass it
+/// back in on [`BlobStore::get`]/[`BlobStore::delete`].
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, id: &Id, bytes: &[u8]) -> Result<String, Error>;
+    async fn get(&self, locator: &str) -> Result<Vec<u8>, Error>;
+    async fn delete(&self, locator: &str) -> Result<(), Error>;
+}
+
+/// Content-addressed filesystem store: bodies are written under `root` keyed by the SHA-256 of
+/// their contents, so identical pastes share storage.
+pub struct FileStore {
+    root: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path_for(&self, locator: &str) -> PathBuf {
+        self.root.join(locator)
+    }
+}
+
+#[async_trait]
+impl BlobStore for FileStore {
+    async fn put(&self, _id: &Id, bytes: &[u8]) -> Result<String, Error> {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        let locator = hex::encode(hasher.finalize());
+
+        tokio::fs::create_dir_all(&self.root).await?;
+        tokio::fs::write(self.path_for(&locator), bytes).await?;
+
+        Ok(locator)
+    }
+
+    async fn get(&self, locator: &str) -> Result<Vec<u8>, Error> {
+        Ok(tokio::fs::read(self.path_for(locator)).await?)
+    }
+
+    async fn delete(&self, locator: &str) -> Result<(), Error> {
+        match tokio::fs::remove_file(self.path_for(locator)).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// SHA-256 of an empty payload, i.e. `x-amz-content-sha256` for bodyless GET/DELETE requests.
+const EMPTY_PAYLOAD_HASH: &str =
+    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// S3-compatible object storage. Bodies are keyed the same way as [`FileStore`] (content
+/// address) under `prefix`, so the locator is portable between backends.
+///
+/// Requests are signed with AWS Signature Version 4 whenever `WASTEBIN_S3_ACCESS_KEY_ID` and
+/// `WASTEBIN_S3_SECRET_ACCESS_KEY` are set; without them, only an unauthenticated/public bucket
+/// will accept the requests this store sends.
+pub struct S3Store {
+    client: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    prefix: String,
+    region: String,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+}
+
+impl S3Store {
+    pub fn new(
+        endpoint: String,
+        bucket: String,
+        prefix: String,
+        region: String,
+        access_key_id: Option<String>,
+        secret_access_key: Option<String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            bucket,
+            prefix,
+            region,
+            access_key_id,
+            secret_access_key,
+        }
+    }
+
+    fn object_url(&self, locator: &str) -> String {
+        let mut segments = vec![self.endpoint.trim_end_matches('/'), self.bucket.as_str()];
+        let prefix = self.prefix.trim_matches('/');
+        if !prefix.is_empty() {
+            segments.push(prefix);
+        }
+        segments.push(locator);
+        segments.join("/")
+    }
+
+    /// Builds the headers for a request, signing it with SigV4 when credentials are configured.
+    fn headers(&self, method: &str, url: &str, payload_hash: &str) -> Result<HeaderMap, Error> {
+        let url = reqwest::Url::parse(url).map_err(|err| Error::Storage(err.to_string()))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::Storage("S3 endpoint has no host".to_string()))?;
+        let host = match url.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(reqwest::header::HOST, header_value(&host)?);
+        headers.insert("x-amz-content-sha256", header_value(payload_hash)?);
+
+        let (Some(access_key_id), Some(secret_access_key)) =
+            (&self.access_key_id, &self.secret_access_key)
+        else {
+            return Ok(headers);
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let amz_date = format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            now.year(),
+            u8::from(now.month()),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+        let date_stamp = format!(
+            "{:04}{:02}{:02}",
+            now.year(),
+            u8::from(now.month()),
+            now.day()
+        );
+        headers.insert("x-amz-date", header_value(&amz_date)?);
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_headers =
+            format!("host:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n");
+        let canonical_request = format!(
+            "{method}\n{}\n\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+            url.path()
+        );
+
+        let credential_scope = format!("{date_stamp}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{credential_scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let k_date = hmac_sha256(format!("AWS4{secret_access_key}").as_bytes(), date_stamp.as_bytes());
+        let k_region = hmac_sha256(&k_date, self.region.as_bytes());
+        let k_service = hmac_sha256(&k_region, b"s3");
+        let k_signing = hmac_sha256(&k_service, b"aws4_request");
+        let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={access_key_id}/{credential_scope}, SignedHeaders={signed_headers}, Signature={signature}"
+        );
+        headers.insert(reqwest::header::AUTHORIZATION, header_value(&authorization)?);
+
+        Ok(headers)
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, Error> {
+    HeaderValue::from_str(value).map_err(|err| Error::Storage(err.to_string()))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts a key of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[async_trait]
+impl BlobStore for S3Store {
+    async fn put(&self, _id: &Id, bytes: &[u8]) -> Result<String, Error> {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        let locator = hex::encode(hasher.finalize());
+        let url = self.object_url(&locator);
+        let headers = self.headers("PUT", &url, &locator)?;
+
+        self.client
+            .put(url)
+            .headers(headers)
+            .body(bytes.to_vec())
+            .send()
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?
+            .error_for_status()
+            .map_err(|err| Error::Storage(err.to_string()))?;
+
+        Ok(locator)
+    }
+
+    async fn get(&self, locator: &str) -> Result<Vec<u8>, Error> {
+        let url = self.object_url(locator);
+        let headers = self.headers("GET", &url, EMPTY_PAYLOAD_HASH)?;
+
+        let response = self
+            .client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?
+            .error_for_status()
+            .map_err(|err| Error::Storage(err.to_string()))?;
+
+        Ok(response
+            .bytes()
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?
+            .to_vec())
+    }
+
+    async fn delete(&self, locator: &str) -> Result<(), Error> {
+        let url = self.object_url(locator);
+        let headers = self.headers("DELETE", &url, EMPTY_PAYLOAD_HASH)?;
+
+        self.client
+            .delete(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?
+            .error_for_status()
+            .map_err(|err| Error::Storage(err.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// Parses `WASTEBIN_STORAGE_URI` and constructs the matching backend, dispatching on the URI
+/// scheme the same way [`crate::db::Database::connect`] does for `WASTEBIN_DATABASE_URL`.
+///
+/// Recognized schemes: `sqlite:` (bodies stay inline, no blob store), `file://<dir>` and
+/// `s3://<bucket>/<prefix>` (optionally pointing `WASTEBIN_S3_ENDPOINT` elsewhere for
+/// S3-compatible providers, and `WASTEBIN_S3_ACCESS_KEY_ID`/`WASTEBIN_S3_SECRET_ACCESS_KEY`/
+/// `WASTEBIN_S3_REGION` at requests with SigV4).
+pub fn from_uri(uri: &str) -> Result<Option<Arc<dyn BlobStore>>, Error> {
+    if let Some(rest) = uri.strip_prefix("file://") {
+        return Ok(Some(Arc::new(FileStore::new(PathBuf::from(rest)))));
+    }
+
+    if let Some(rest) = uri.strip_prefix("s3://") {
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        let endpoint = std::env::var("WASTEBIN_S3_ENDPOINT")
+            .unwrap_or_else(|_| "https://s3.amazonaws.com".to_string());
+        let region = std::env::var("WASTEBIN_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let access_key_id = std::env::var("WASTEBIN_S3_ACCESS_KEY_ID").ok();
+        let secret_access_key = std::env::var("WASTEBIN_S3_SECRET_ACCESS_KEY").ok();
+
+        return Ok(Some(Arc::new(S3Store::new(
+            endpoint,
+            bucket.to_string(),
+            prefix.to_string(),
+            region,
+            access_key_id,
+            secret_access_key,
+        ))));
+    }
+
+    if uri.starts_with("sqlite:") {
+        return Ok(None);
+    }
+
+    Err(Error::Storage(format!("unrecognized storage URI: {uri}")))
+}
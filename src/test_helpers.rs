@@ -1,4 +1,5 @@
 use crate::db::{self, Database};
+use crate::storage::BlobStore;
 use crate::{cache, token};
 use axum::body::HttpBody;
 use axum::BoxError;
@@ -55,9 +56,37 @@ impl Client {
     }
 }
 
-pub(crate) fn make_app() -> Result<axum::Router, Box<dyn std::error::Error>> {
+/// A fully wired app plus the handles tests need to exercise the auth and paste flows: the
+/// `Issuer` to mint tokens and the `Database` to issue refresh tokens against directly.
+pub(crate) struct TestApp {
+    pub(crate) router: axum::Router,
+    pub(crate) issuer: Arc<token::Issuer>,
+    pub(crate) database: Database,
+}
+
+fn build(database: Database, cache_layer: cache::Layer) -> TestApp {
+    let issuer = Arc::new(token::Issuer::new(&[1, 2, 3, 4], "test".to_string()));
+    let router = crate::make_app(cache_layer, issuer.clone(), 4096, 1024, 4096);
+
+    TestApp {
+        router,
+        issuer,
+        database,
+    }
+}
+
+pub(crate) fn make_app() -> Result<TestApp, Box<dyn std::error::Error>> {
     let database = Database::new(db::Open::Memory)?;
     let cache_layer = cache::Layer::new(database.clone(), NonZeroUsize::new(128).unwrap());
-    let issuer = token::Issuer::new(&[1, 2, 3, 4], "test".to_string());
-    Ok(crate::make_app(cache_layer, Arc::new(issuer), 4096))
+    Ok(build(database, cache_layer))
+}
+
+pub(crate) fn make_app_with_storage(
+    blob_store: Option<Arc<dyn BlobStore>>,
+    threshold: usize,
+) -> Result<TestApp, Box<dyn std::error::Error>> {
+    let database = Database::new(db::Open::Memory)?;
+    let cache_layer = cache::Layer::new(database.clone(), NonZeroUsize::new(128).unwrap())
+        .with_storage(blob_store, threshold);
+    Ok(build(database, cache_layer))
 }
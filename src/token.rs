@@ -0,0 +1,208 @@
+use crate::cache::Layer;
+use crate::db::Database;
+use crate::Error;
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{header, StatusCode};
+use axum::Extension;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// How long a minted access token remains valid for.
+const ACCESS_TOKEN_TTL: time::Duration = time::Duration::minutes(15);
+/// How long a refresh token can be exchanged for new access tokens.
+const REFRESH_TOKEN_TTL: time::Duration = time::Duration::days(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Role {
+    User,
+    Admin,
+}
+
+impl Role {
+    fn as_str(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            _ => Err(Error::TokenValidation("unknown role".to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    role: Role,
+    iss: String,
+    exp: i64,
+}
+
+/// A freshly minted pair of tokens returned to a client on authentication.
+pub struct AuthTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+/// Mints and validates access tokens, and manages the refresh tokens backing them.
+pub struct Issuer {
+    secret: Vec<u8>,
+    iss: String,
+}
+
+fn hash_refresh_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+impl Issuer {
+    pub fn new(secret: &[u8], iss: String) -> Self {
+        Self {
+            secret: secret.to_vec(),
+            iss,
+        }
+    }
+
+    /// Mint a single self-contained access JWT for `user`, valid for `ACCESS_TOKEN_TTL` like the
+    /// access token from [`Issuer::issue_pair`]. Used to mint a fresh access token on
+    /// [`Issuer::refresh`]; it is short-lived by design, not an operator-issued long-lived
+    /// credential.
+    pub fn issue(&self, user: User) -> Result<String, Error> {
+        let exp = (OffsetDateTime::now_utc() + ACCESS_TOKEN_TTL).unix_timestamp();
+        self.encode(&user, exp)
+    }
+
+    /// Mint a short-lived access token plus an opaque refresh token, persisting the refresh
+    /// token's hash so it can later be revoked or expired server-side.
+    pub fn issue_pair(&self, user: User, database: &Database) -> Result<AuthTokens, Error> {
+        let now = OffsetDateTime::now_utc();
+        let access_token = self.encode(&user, (now + ACCESS_TOKEN_TTL).unix_timestamp())?;
+
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let refresh_token = hex::encode(bytes);
+        let token_hash = hash_refresh_token(&refresh_token);
+
+        database.insert_refresh_token(
+            &token_hash,
+            &user.name,
+            user.role.as_str(),
+            now.unix_timestamp(),
+            (now + REFRESH_TOKEN_TTL).unix_timestamp(),
+        )?;
+
+        Ok(AuthTokens {
+            access_token,
+            refresh_token,
+        })
+    }
+
+    /// Exchange a refresh token for a fresh access token, rejecting it if it is unknown, revoked
+    /// or expired.
+    pub fn refresh(&self, refresh_token: &str, database: &Database) -> Result<String, Error> {
+        let token_hash = hash_refresh_token(refresh_token);
+        let (name, role) = database.get_valid_refresh_token(&token_hash)?;
+        let role: Role = role.parse()?;
+
+        if database.is_user_blocked(&name)? {
+            return Err(Error::UserBlocked);
+        }
+
+        self.issue(User { name, role })
+    }
+
+    fn encode(&self, user: &User, exp: i64) -> Result<String, Error> {
+        let claims = Claims {
+            sub: user.name.clone(),
+            role: user.role,
+            iss: self.iss.clone(),
+            exp,
+        };
+
+        jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(&self.secret),
+        )
+        .map_err(|err| Error::TokenCreation(err.to_string()))
+    }
+
+    /// Validate an access token, rejecting it if the signature, issuer or expiry don't check out,
+    /// or if the user it names has since been blocked.
+    pub fn validate(&self, token: &str, database: &Database) -> Result<User, Error> {
+        let mut validation = Validation::default();
+        validation.set_issuer(&[&self.iss]);
+
+        let data = jsonwebtoken::decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(&self.secret),
+            &validation,
+        )
+        .map_err(|err| Error::TokenValidation(err.to_string()))?;
+
+        if database.is_user_blocked(&data.claims.sub)? {
+            return Err(Error::UserBlocked);
+        }
+
+        Ok(User {
+            name: data.claims.sub,
+            role: data.claims.role,
+        })
+    }
+}
+
+/// Axum extractor that guards a route behind a valid, non-blocked access token. Add it as a
+/// handler parameter (before any body-consuming extractor) to require `Authorization: Bearer
+/// <token>` and reject the request with `401 Unauthorized` otherwise.
+pub struct AuthUser(pub User);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(issuer) = Extension::<Arc<Issuer>>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let Extension(cache) = Extension::<Layer>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        issuer
+            .validate(token, cache.database())
+            .map(AuthUser)
+            .map_err(StatusCode::from)
+    }
+}